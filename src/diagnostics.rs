@@ -0,0 +1,102 @@
+use crate::expr::core::{CoreExpr, TerminalMatcher};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// A `Choice` branch whose terminal predicate is already covered by an
+    /// earlier branch, so it can never be reached.
+    RedundantBranch,
+    /// A subexpression that always matches the empty string (`Null`, `Repeat`,
+    /// or a `Choice`/`Sequence` that reduces to one), e.g. `Repeat(Null)`.
+    Irrefutable,
+}
+
+/// A single finding from [`analyze`], locating the offending node by the path
+/// of child indices from the root of the expression tree.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub severity: Severity,
+    pub path: Vec<usize>,
+    pub message: String,
+}
+
+/// Walks `expr` and reports unreachable/redundant `Choice` branches and
+/// irrefutable subexpressions, so library users can lint their patterns before
+/// compiling them.
+pub fn analyze<T: TerminalMatcher>(expr: &CoreExpr<T>) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut path = Vec::new();
+    analyze_node(expr, &mut path, &mut diagnostics);
+    diagnostics
+}
+
+fn analyze_node<T: TerminalMatcher>(expr: &CoreExpr<T>, path: &mut Vec<usize>, out: &mut Vec<Diagnostic>) {
+    if is_irrefutable(expr) {
+        out.push(Diagnostic {
+            kind: DiagnosticKind::Irrefutable,
+            severity: Severity::Warning,
+            path: path.clone(),
+            message: "subexpression always matches the empty string".to_string(),
+        });
+    }
+    match expr {
+        CoreExpr::Terminal(_) | CoreExpr::Null => {}
+        CoreExpr::Group(_, inner) => {
+            path.push(0);
+            analyze_node(inner, path, out);
+            path.pop();
+        }
+        CoreExpr::Sequence(exprs) => {
+            for (i, child) in exprs.iter().enumerate() {
+                path.push(i);
+                analyze_node(child, path, out);
+                path.pop();
+            }
+        }
+        CoreExpr::Choice(exprs) => {
+            for (i, child) in exprs.iter().enumerate() {
+                if let CoreExpr::Terminal(matcher) = child {
+                    let earlier_subsumer = exprs[..i].iter().position(|earlier| {
+                        matches!(earlier, CoreExpr::Terminal(earlier_matcher) if earlier_matcher.subsumes(matcher))
+                    });
+                    if let Some(j) = earlier_subsumer {
+                        let mut redundant_path = path.clone();
+                        redundant_path.push(i);
+                        out.push(Diagnostic {
+                            kind: DiagnosticKind::RedundantBranch,
+                            severity: Severity::Warning,
+                            path: redundant_path,
+                            message: format!("branch {i} can never match: branch {j} already accepts everything it does"),
+                        });
+                    }
+                }
+                path.push(i);
+                analyze_node(child, path, out);
+                path.pop();
+            }
+        }
+        CoreExpr::Repeat(inner) => {
+            path.push(0);
+            analyze_node(inner, path, out);
+            path.pop();
+        }
+    }
+}
+
+/// Whether `expr` matches the empty string no matter what it contains.
+fn is_irrefutable<T: TerminalMatcher>(expr: &CoreExpr<T>) -> bool {
+    match expr {
+        CoreExpr::Null => true,
+        CoreExpr::Repeat(_) => true,
+        CoreExpr::Terminal(_) => false,
+        CoreExpr::Group(_, inner) => is_irrefutable(inner),
+        CoreExpr::Choice(exprs) => exprs.iter().any(is_irrefutable),
+        CoreExpr::Sequence(exprs) => exprs.iter().all(is_irrefutable),
+    }
+}
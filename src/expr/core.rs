@@ -6,6 +6,17 @@ use std::{
 pub trait TerminalMatcher: Debug {
     type Terminal: Debug;
     fn matches(&self, terminal: &Self::Terminal) -> bool;
+
+    /// Reports whether everything `other` matches is also matched by `self`,
+    /// including the case where the two are equivalent. Used by the
+    /// `diagnostics` module to flag `Choice` branches that can never be
+    /// reached because an earlier branch already covers them. The comparison
+    /// is undecidable in general, so this defaults to `false`; matchers that
+    /// can decide it (e.g. `CharMatcher` comparing `CharRule`s) should
+    /// override it.
+    fn subsumes(&self, _other: &Self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug)]
@@ -14,23 +25,93 @@ pub enum CoreExpr<T: TerminalMatcher> {
     Sequence(Vec<CoreExpr<T>>),
     Choice(Vec<CoreExpr<T>>),
     Repeat(Box<CoreExpr<T>>),
+    /// A labeled subexpression whose matched span can be recovered from
+    /// `Matcher::capture`.
+    Group(usize, Box<CoreExpr<T>>),
     Null,
 }
 
 impl<T: TerminalMatcher> CoreExpr<T> {
-    pub fn compile(&self) -> Matcher<T> {
-        Matcher::new(self)
+    pub fn compile(&self, opts: impl Into<MatcherOptions>) -> Matcher<'_, T> {
+        Matcher::new(self, opts.into())
+    }
+
+    /// Convenience for `compile(CompileOptions::default())`.
+    pub fn compile_default(&self) -> Matcher<'_, T> {
+        self.compile(CompileOptions::default())
+    }
+}
+
+/// Options controlling how a [`CoreExpr`] is compiled into a [`Matcher`],
+/// mirroring how compilers thread an options value through `parse`/`compile`.
+#[derive(Debug, Clone, Copy)]
+pub struct CompileOptions {
+    /// Precompute the epsilon-closure of every NFA state once, at `Matcher::new`
+    /// time, instead of walking epsilon transitions on every step of
+    /// `match_sequence`/`find`. Pure optimization; does not change match results.
+    pub precompute_epsilon_closures: bool,
+    /// Require matches to start at the beginning of the input, as opposed to the
+    /// unanchored search `find`/`find_iter` perform by default.
+    pub anchored: bool,
+    /// Fold case when comparing terminals, where the underlying `TerminalMatcher`
+    /// supports it (currently only `CharMatcher`). Only consulted by
+    /// `ExprExtension::to_core_expr` while building the `CoreExpr` tree — see
+    /// `MatcherOptions` for why `Matcher` itself never sees this flag.
+    pub case_insensitive: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            precompute_epsilon_closures: true,
+            anchored: false,
+            case_insensitive: false,
+        }
+    }
+}
+
+/// The subset of `CompileOptions` that `Matcher::new`/`CoreExpr::compile`
+/// actually consume. `case_insensitive` is deliberately excluded: it only
+/// matters while `to_core_expr` is building terminals (it gets baked into
+/// e.g. `CharMatcher`), so giving `compile` its own copy of the flag would let
+/// a caller build the tree with one `CompileOptions` and compile it with
+/// another, silently dropping or stale-applying `case_insensitive`. Because
+/// `compile` can only ever receive a `MatcherOptions`, that divergence is
+/// impossible by construction; `From<CompileOptions>` lets callers keep
+/// passing the same `CompileOptions` value to both phases.
+#[derive(Debug, Clone, Copy)]
+pub struct MatcherOptions {
+    pub precompute_epsilon_closures: bool,
+    pub anchored: bool,
+}
+
+impl From<CompileOptions> for MatcherOptions {
+    fn from(opts: CompileOptions) -> Self {
+        MatcherOptions {
+            precompute_epsilon_closures: opts.precompute_epsilon_closures,
+            anchored: opts.anchored,
+        }
     }
 }
 
 pub trait ExprExtension<'a, T: TerminalMatcher> {
-    fn into_core_expr(&self) -> CoreExpr<T>;
+    fn to_core_expr(&self, opts: &CompileOptions) -> CoreExpr<T>;
+}
+
+/// A group-open/close marker attached to a specific epsilon edge, inserted by
+/// `expand` when it encounters a `CoreExpr::Group`.
+#[derive(Debug, Clone, Copy)]
+enum GroupTag {
+    Open(usize),
+    Close(usize),
 }
 
 #[derive(Debug)]
 struct TransitionFunc<'a, T: TerminalMatcher> {
     terminals: BTreeMap<usize, &'a T>,
     epsilons: BTreeSet<usize>,
+    /// Tags carried by a subset of `epsilons`, keyed by the same target state.
+    group_tags: BTreeMap<usize, GroupTag>,
 }
 
 impl<'a, T: TerminalMatcher> TransitionFunc<'a, T> {
@@ -47,20 +128,29 @@ pub struct Matcher<'a, T: TerminalMatcher> {
     transition_funcs: Vec<TransitionFunc<'a, T>>,
     start_state: usize,
     end_state: usize,
+    opts: MatcherOptions,
+    /// Precomputed epsilon-closure for each state, indexed by state id, when
+    /// `opts.precompute_epsilon_closures` is set.
+    epsilon_closures: Option<Vec<BTreeSet<usize>>>,
 }
 
 impl<'a, T: TerminalMatcher> Matcher<'a, T> {
-    pub fn new(expr: &'a CoreExpr<T>) -> Self {
+    pub fn new(expr: &'a CoreExpr<T>, opts: MatcherOptions) -> Self {
         let mut matcher = Matcher {
             transition_funcs: Vec::new(),
             start_state: 0,
             end_state: 1,
+            opts,
+            epsilon_closures: None,
         };
         let start_state = matcher.create_new_state();
         let end_state = matcher.create_new_state();
         assert_eq!(start_state, matcher.start_state);
         assert_eq!(end_state, matcher.end_state);
         matcher.expand(expr, start_state, end_state);
+        if opts.precompute_epsilon_closures {
+            matcher.epsilon_closures = Some(matcher.compute_epsilon_closures());
+        }
         matcher
     }
 
@@ -69,6 +159,7 @@ impl<'a, T: TerminalMatcher> Matcher<'a, T> {
         self.transition_funcs.push(TransitionFunc {
             terminals: BTreeMap::new(),
             epsilons: BTreeSet::new(),
+            group_tags: BTreeMap::new(),
         });
         state
     }
@@ -76,7 +167,18 @@ impl<'a, T: TerminalMatcher> Matcher<'a, T> {
     fn expand(&mut self, expr: &'a CoreExpr<T>, start_state: usize, end_state: usize) {
         match expr {
             CoreExpr::Terminal(matcher) => {
-                self.transition_funcs[start_state].terminals.insert(end_state, matcher);
+                // `terminals` is keyed by target state, so inserting straight
+                // into `start_state` keyed by `end_state` would collide when
+                // two `Terminal` branches under the same `Choice` happen to
+                // share both endpoints (e.g. `a|b`, both one character long):
+                // the second `insert` would silently overwrite the first and
+                // only the later branch would ever match. Route through a
+                // fresh intermediate state instead, so every `Terminal` edge
+                // gets a distinct key regardless of what else shares its
+                // start/end states.
+                let mid_state = self.create_new_state();
+                self.transition_funcs[start_state].terminals.insert(mid_state, matcher);
+                self.transition_funcs[mid_state].epsilons.insert(end_state);
             }
             CoreExpr::Sequence(exprs) => {
                 let mut prev_state = start_state;
@@ -97,31 +199,62 @@ impl<'a, T: TerminalMatcher> Matcher<'a, T> {
                 self.transition_funcs[end_state].epsilons.insert(start_state);
                 self.expand(expr, start_state, end_state);
             }
+            CoreExpr::Group(group_id, expr) => {
+                let inner_start = self.create_new_state();
+                let inner_end = self.create_new_state();
+                self.transition_funcs[start_state].epsilons.insert(inner_start);
+                self.transition_funcs[start_state]
+                    .group_tags
+                    .insert(inner_start, GroupTag::Open(*group_id));
+                self.expand(expr, inner_start, inner_end);
+                self.transition_funcs[inner_end].epsilons.insert(end_state);
+                self.transition_funcs[inner_end].group_tags.insert(end_state, GroupTag::Close(*group_id));
+            }
             CoreExpr::Null => {
                 self.transition_funcs[start_state].epsilons.insert(end_state);
             }
         }
     }
 
-    pub fn match_sequence(&self, string: &[T::Terminal]) -> bool {
-        println!("Matching sequence: {:?}", string);
-        let extend_epsilons = |states: &mut BTreeSet<usize>| {
-            // Expand epsilon transitions until no more states are added
-            // TODO: resolve epsilon expansion when the matcher is constructed
-            loop {
-                let mut new_states = BTreeSet::new();
-                for state in states.iter() {
-                    new_states.extend(self.transition_funcs[*state].epsilons.iter());
-                }
-                if new_states.is_subset(states) {
-                    break;
-                }
-                states.extend(new_states);
+    fn compute_epsilon_closures(&self) -> Vec<BTreeSet<usize>> {
+        (0..self.transition_funcs.len())
+            .map(|state| {
+                let mut closure = BTreeSet::new();
+                closure.insert(state);
+                self.expand_epsilons(&mut closure);
+                closure
+            })
+            .collect()
+    }
+
+    /// Expand epsilon transitions until no more states are added.
+    fn expand_epsilons(&self, states: &mut BTreeSet<usize>) {
+        loop {
+            let mut new_states = BTreeSet::new();
+            for state in states.iter() {
+                new_states.extend(self.transition_funcs[*state].epsilons.iter());
             }
-        };
+            if new_states.is_subset(states) {
+                break;
+            }
+            states.extend(new_states);
+        }
+    }
+
+    /// Resolves epsilon transitions for `states`, either by walking them on the
+    /// fly or, when `opts.precompute_epsilon_closures` is set, by looking up the
+    /// closure computed once in `Matcher::new`.
+    fn extend_epsilons(&self, states: &mut BTreeSet<usize>) {
+        match &self.epsilon_closures {
+            Some(closures) => *states = states.iter().flat_map(|state| closures[*state].iter().copied()).collect(),
+            None => self.expand_epsilons(states),
+        }
+    }
 
+    pub fn match_sequence(&self, string: &[T::Terminal]) -> bool {
         let mut current_states = BTreeSet::new();
-        current_states.insert(0);
+        current_states.insert(self.start_state);
+        self.extend_epsilons(&mut current_states);
         for terminal in string {
             if current_states.is_empty() {
                 return false;
@@ -132,9 +265,193 @@ impl<'a, T: TerminalMatcher> Matcher<'a, T> {
                 next_states.extend(transition_func.get_terminal_transitions(terminal));
             }
             current_states = next_states;
-            extend_epsilons(&mut current_states);
-            println!("Current states: {:?}", current_states);
+            self.extend_epsilons(&mut current_states);
         }
         current_states.contains(&self.end_state)
     }
+
+    /// Runs the NFA starting at `seq[start..]` and returns the end index of the
+    /// longest match beginning at `start`, if any (matching the greedy, longest-match
+    /// semantics used elsewhere in the crate).
+    fn longest_match_from(&self, seq: &[T::Terminal], start: usize) -> Option<usize> {
+        let mut current_states = BTreeSet::new();
+        current_states.insert(self.start_state);
+        self.extend_epsilons(&mut current_states);
+        let mut longest_end = current_states.contains(&self.end_state).then_some(start);
+        for (offset, terminal) in seq[start..].iter().enumerate() {
+            if current_states.is_empty() {
+                break;
+            }
+            let mut next_states = BTreeSet::new();
+            for state in &current_states {
+                next_states.extend(self.transition_funcs[*state].get_terminal_transitions(terminal));
+            }
+            current_states = next_states;
+            self.extend_epsilons(&mut current_states);
+            if current_states.contains(&self.end_state) {
+                longest_end = Some(start + offset + 1);
+            }
+        }
+        longest_end
+    }
+
+    /// Finds the leftmost match in `seq`. Unless `opts.anchored` is set, this
+    /// searches unanchored from every start position in turn; anchored matchers
+    /// only try position 0. Returns the half-open `[start, end)` span of the
+    /// longest match found.
+    pub fn find(&self, seq: &[T::Terminal]) -> Option<(usize, usize)> {
+        if self.opts.anchored {
+            return self.longest_match_from(seq, 0).map(|end| (0, end));
+        }
+        (0..=seq.len()).find_map(|start| self.longest_match_from(seq, start).map(|end| (start, end)))
+    }
+
+    /// Returns an iterator over non-overlapping match spans in `seq`, scanning
+    /// left to right, following the `str::match_indices` convention.
+    pub fn find_iter<'s>(&'s self, seq: &'s [T::Terminal]) -> FindIter<'s, 'a, T> {
+        FindIter {
+            matcher: self,
+            seq,
+            pos: 0,
+        }
+    }
+
+    /// Alias for [`Matcher::find_iter`] that only yields the matched spans,
+    /// mirroring `str::matches`.
+    pub fn matches<'s>(&'s self, seq: &'s [T::Terminal]) -> FindIter<'s, 'a, T> {
+        self.find_iter(seq)
+    }
+
+    /// Matches the whole of `seq`, like `match_sequence`, but also recovers the
+    /// span each `CoreExpr::Group` captured via a tagged Thompson simulation:
+    /// each active state is carried by a `Thread` with its own tag map, and
+    /// when two threads would occupy the same state, the first one reached
+    /// (i.e. the higher-priority one, following the left-to-right order
+    /// `expand` lays out `Choice` branches and `Repeat` bodies) wins.
+    pub fn capture(&self, seq: &[T::Terminal]) -> Option<Captures> {
+        let start_thread = Thread {
+            state: self.start_state,
+            groups: BTreeMap::new(),
+        };
+        let mut threads = self.expand_thread_epsilons(vec![start_thread], 0);
+        for (pos, terminal) in seq.iter().enumerate() {
+            if threads.is_empty() {
+                return None;
+            }
+            let mut next_threads = Vec::new();
+            let mut seen = BTreeSet::new();
+            for thread in &threads {
+                for (&target, matcher) in &self.transition_funcs[thread.state].terminals {
+                    if matcher.matches(terminal) && seen.insert(target) {
+                        let mut next = thread.clone();
+                        next.state = target;
+                        next_threads.push(next);
+                    }
+                }
+            }
+            threads = self.expand_thread_epsilons(next_threads, pos + 1);
+        }
+        let winner = threads.into_iter().find(|thread| thread.state == self.end_state)?;
+        Some(Captures {
+            groups: winner
+                .groups
+                .into_iter()
+                .filter_map(|(group_id, (start, end))| end.map(|end| (group_id, (start, end))))
+                .collect(),
+        })
+    }
+
+    /// Expands epsilon transitions for a set of threads at input position `pos`,
+    /// applying any `GroupTag`s found along the way, and keeping only the first
+    /// (highest priority) thread to reach each state.
+    fn expand_thread_epsilons(&self, threads: Vec<Thread>, pos: usize) -> Vec<Thread> {
+        let mut visited = BTreeSet::new();
+        let mut out = Vec::new();
+        for thread in threads {
+            self.visit_thread_epsilons(thread, pos, &mut visited, &mut out);
+        }
+        out
+    }
+
+    fn visit_thread_epsilons(&self, thread: Thread, pos: usize, visited: &mut BTreeSet<usize>, out: &mut Vec<Thread>) {
+        if !visited.insert(thread.state) {
+            return;
+        }
+        let transition_func = &self.transition_funcs[thread.state];
+        for &target in transition_func.epsilons.iter() {
+            let mut next = thread.clone();
+            next.state = target;
+            if let Some(tag) = transition_func.group_tags.get(&target) {
+                next.apply_tag(*tag, pos);
+            }
+            self.visit_thread_epsilons(next, pos, visited, out);
+        }
+        out.push(thread);
+    }
+}
+
+/// A single active path through the NFA during `Matcher::capture`, carrying
+/// the group spans observed so far. A group present in the map but not yet
+/// closed is recorded as `(start, None)`.
+#[derive(Debug, Clone)]
+struct Thread {
+    state: usize,
+    groups: BTreeMap<usize, (usize, Option<usize>)>,
+}
+
+impl Thread {
+    fn apply_tag(&mut self, tag: GroupTag, pos: usize) {
+        match tag {
+            GroupTag::Open(group_id) => {
+                self.groups.insert(group_id, (pos, None));
+            }
+            GroupTag::Close(group_id) => {
+                let start = self.groups.get(&group_id).map_or(pos, |&(start, _)| start);
+                self.groups.insert(group_id, (start, Some(pos)));
+            }
+        }
+    }
+}
+
+/// Per-group `[start, end)` spans recovered by `Matcher::capture`.
+#[derive(Debug, Clone, Default)]
+pub struct Captures {
+    groups: BTreeMap<usize, (usize, usize)>,
+}
+
+impl Captures {
+    /// Returns the span captured by group `id`, if it participated in the match.
+    pub fn get(&self, id: usize) -> Option<(usize, usize)> {
+        self.groups.get(&id).copied()
+    }
+}
+
+/// Iterator over non-overlapping match spans produced by [`Matcher::find_iter`].
+#[derive(Debug)]
+pub struct FindIter<'s, 'a, T: TerminalMatcher> {
+    matcher: &'s Matcher<'a, T>,
+    seq: &'s [T::Terminal],
+    pos: usize,
+}
+
+impl<'s, 'a, T: TerminalMatcher> Iterator for FindIter<'s, 'a, T> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos <= self.seq.len() {
+            if self.matcher.opts.anchored && self.pos > 0 {
+                return None;
+            }
+            if let Some(end) = self.matcher.longest_match_from(self.seq, self.pos) {
+                let start = self.pos;
+                self.pos = if end > start { end } else { end + 1 };
+                return Some((start, end));
+            }
+            if self.matcher.opts.anchored {
+                return None;
+            }
+            self.pos += 1;
+        }
+        None
+    }
 }
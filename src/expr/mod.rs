@@ -0,0 +1,4 @@
+pub mod char;
+pub mod core;
+pub mod music;
+pub mod parse;
@@ -0,0 +1,130 @@
+use super::{Duration, Note, NoteEvent};
+
+/// All `Duration`s known to the crate, in the order ticks are checked when
+/// quantizing a raw elapsed-tick count.
+fn all_durations() -> [Duration; 12] {
+    [
+        Duration::Whole,
+        Duration::Half,
+        Duration::Third,
+        Duration::Quarter,
+        Duration::Sixth,
+        Duration::Eighth,
+        Duration::Twelfth,
+        Duration::Sixteenth,
+        Duration::TwentyFourth,
+        Duration::ThirtySecond,
+        Duration::FortyEighth,
+        Duration::SixtyFourth,
+    ]
+}
+
+/// Snaps `raw_ticks` onto the nearest `Duration`'s tick count, as long as it
+/// is within `tolerance_ticks` of it; otherwise the raw tick count is kept
+/// as-is (never truncated), since it describes real performance data — e.g. a
+/// multi-beat rest — that may be both larger than any notatable `Duration`
+/// and not fall within tolerance of one.
+fn quantize_ticks(raw_ticks: u32, tolerance_ticks: u32) -> u32 {
+    let mut best = raw_ticks;
+    let mut best_diff = u32::MAX;
+    for duration in all_durations() {
+        let ticks = duration.as_ticks() as u32;
+        let diff = raw_ticks.abs_diff(ticks);
+        if diff <= tolerance_ticks && diff < best_diff {
+            best = ticks;
+            best_diff = diff;
+        }
+    }
+    best
+}
+
+/// Converts a monophonic sequence of timed MIDI note-on/note-off pairs
+/// `(pitch, on_tick, off_tick)`, given in chronological order, into the
+/// crate's `Vec<Note>` representation: successive pitches become
+/// `NoteEvent::Interval(delta)`, gaps between a note-off and the next note-on
+/// become `NoteEvent::Rest`, and the sequence is terminated with a
+/// `NoteEvent::Last`. Each note's duration is quantized onto the nearest
+/// `Duration` within `quantize_tolerance_ticks`, so a compiled `NoteMatcher`
+/// built from exact `DurationRule`s can still match real performance timing.
+pub fn notes_from_midi_events(events: &[(u8, u32, u32)], quantize_tolerance_ticks: u32) -> Vec<Note> {
+    let mut notes = Vec::new();
+    let mut prev_pitch: Option<u8> = None;
+    let mut prev_off_tick: Option<u32> = None;
+    for &(pitch, on_tick, off_tick) in events {
+        if let Some(prev_off_tick) = prev_off_tick {
+            let gap = on_tick.saturating_sub(prev_off_tick);
+            if gap > 0 {
+                notes.push(Note {
+                    event: NoteEvent::Rest,
+                    duration: quantize_ticks(gap, quantize_tolerance_ticks),
+                });
+            }
+        }
+        let interval = match prev_pitch {
+            Some(prev_pitch) => (pitch as i16 - prev_pitch as i16) as i8,
+            None => 0,
+        };
+        notes.push(Note {
+            event: NoteEvent::Interval(interval),
+            duration: quantize_ticks(off_tick.saturating_sub(on_tick), quantize_tolerance_ticks),
+        });
+        prev_pitch = Some(pitch);
+        prev_off_tick = Some(off_tick);
+    }
+    notes.push(Note {
+        event: NoteEvent::Last,
+        duration: 0,
+    });
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes_from_midi_events_basic_melody() {
+        // quarter notes (48 ticks) at C4, D4, E4, back to back.
+        let events = [(60, 0, 48), (62, 48, 96), (64, 96, 144)];
+        let notes = notes_from_midi_events(&events, 0);
+        assert_eq!(notes.len(), 4);
+        assert!(matches!(notes[0].event, NoteEvent::Interval(0)));
+        assert_eq!(notes[0].duration, 48);
+        assert!(matches!(notes[1].event, NoteEvent::Interval(2)));
+        assert!(matches!(notes[2].event, NoteEvent::Interval(2)));
+        assert!(matches!(notes[3].event, NoteEvent::Last));
+    }
+
+    #[test]
+    fn test_notes_from_midi_events_emits_rest_for_gap() {
+        let events = [(60, 0, 48), (60, 96, 144)];
+        let notes = notes_from_midi_events(&events, 0);
+        assert_eq!(notes.len(), 4);
+        assert!(matches!(notes[1].event, NoteEvent::Rest));
+        assert_eq!(notes[1].duration, 48);
+    }
+
+    #[test]
+    fn test_notes_from_midi_events_quantizes_within_tolerance() {
+        let events = [(60, 0, 50)];
+        let notes = notes_from_midi_events(&events, 5);
+        assert_eq!(notes[0].duration, 48);
+    }
+
+    #[test]
+    fn test_notes_from_midi_events_keeps_raw_ticks_outside_tolerance() {
+        let events = [(60, 0, 70)];
+        let notes = notes_from_midi_events(&events, 5);
+        assert_eq!(notes[0].duration, 70);
+    }
+
+    #[test]
+    fn test_notes_from_midi_events_does_not_truncate_long_rests() {
+        // A rest far longer than a whole note (192 ticks) must be reported in
+        // full, not clamped to fit in a u8.
+        let events = [(60, 0, 48), (60, 1000, 1048)];
+        let notes = notes_from_midi_events(&events, 0);
+        assert!(matches!(notes[1].event, NoteEvent::Rest));
+        assert_eq!(notes[1].duration, 952);
+    }
+}
@@ -1,4 +1,6 @@
-use super::core::{CoreExpr, ExprExtension, TerminalMatcher};
+use super::core::{CompileOptions, CoreExpr, ExprExtension, TerminalMatcher};
+
+pub mod import;
 
 #[derive(Debug, Clone)]
 pub enum NoteEvent {
@@ -10,7 +12,7 @@ pub enum NoteEvent {
 #[derive(Debug, Clone)]
 pub struct Note {
     event: NoteEvent,
-    duration: u8, // use MIDI ticks
+    duration: u32, // use MIDI ticks
 }
 
 #[derive(Debug, Clone)]
@@ -133,11 +135,11 @@ impl TerminalMatcher for NoteMatcher {
             IntervalRule::Last => matches!(terminal.event, NoteEvent::Last),
         }) && self.rule.duration.iter().any(|duration_rule| match duration_rule {
             DurationRule::Any => true,
-            DurationRule::Exact(duration) => terminal.duration == duration.as_ticks(),
-            DurationRule::MultipleOf(duration) => terminal.duration % duration.as_ticks() == 0,
-            DurationRule::DoublingOf(duration) => terminal.duration % (duration.as_ticks() * 2) == 0,
+            DurationRule::Exact(duration) => terminal.duration == duration.as_ticks() as u32,
+            DurationRule::MultipleOf(duration) => terminal.duration % duration.as_ticks() as u32 == 0,
+            DurationRule::DoublingOf(duration) => terminal.duration % (duration.as_ticks() as u32 * 2) == 0,
             DurationRule::ExactPlusMultipleOf(duration, multiple) => {
-                terminal.duration == duration.as_ticks() || terminal.duration % multiple.as_ticks() == 0
+                terminal.duration == duration.as_ticks() as u32 || terminal.duration % multiple.as_ticks() as u32 == 0
             }
         })
     }
@@ -150,18 +152,27 @@ pub enum NoteExpr {
     OneOrMore(Box<NoteExpr>),
     ZeroOrOne(Box<NoteExpr>),
     Repeat(Box<NoteExpr>),
+    /// A labeled subexpression, recoverable after a match via `Matcher::capture`.
+    Group(usize, Box<NoteExpr>),
     Null,
 }
 
 impl ExprExtension<'_, NoteMatcher> for NoteExpr {
-    fn into_core_expr(&self) -> CoreExpr<NoteMatcher> {
+    fn to_core_expr(&self, _opts: &CompileOptions) -> CoreExpr<NoteMatcher> {
         match self {
             NoteExpr::Note(rule) => CoreExpr::Terminal(NoteMatcher { rule: rule.clone() }),
-            NoteExpr::Sequence(exprs) => CoreExpr::Sequence(exprs.iter().map(|expr| expr.into_core_expr()).collect()),
-            NoteExpr::Choice(exprs) => CoreExpr::Choice(exprs.iter().map(|expr| expr.into_core_expr()).collect()),
-            NoteExpr::OneOrMore(expr) => CoreExpr::OneOrMore(Box::new(expr.into_core_expr())),
-            NoteExpr::ZeroOrOne(expr) => CoreExpr::ZeroOrOne(Box::new(expr.into_core_expr())),
-            NoteExpr::Repeat(expr) => CoreExpr::Repeat(Box::new(expr.into_core_expr())),
+            NoteExpr::Sequence(exprs) => CoreExpr::Sequence(exprs.iter().map(|expr| expr.to_core_expr(_opts)).collect()),
+            NoteExpr::Choice(exprs) => CoreExpr::Choice(exprs.iter().map(|expr| expr.to_core_expr(_opts)).collect()),
+            // `CoreExpr` has no repetition variants beyond `Repeat`, so these
+            // desugar the same way a regex engine desugars `+`/`?` onto `*`:
+            // one-or-more is the expression followed by zero-or-more of
+            // itself, and zero-or-one is a choice between it and nothing.
+            NoteExpr::OneOrMore(expr) => {
+                CoreExpr::Sequence(vec![expr.to_core_expr(_opts), CoreExpr::Repeat(Box::new(expr.to_core_expr(_opts)))])
+            }
+            NoteExpr::ZeroOrOne(expr) => CoreExpr::Choice(vec![expr.to_core_expr(_opts), CoreExpr::Null]),
+            NoteExpr::Repeat(expr) => CoreExpr::Repeat(Box::new(expr.to_core_expr(_opts))),
+            NoteExpr::Group(id, expr) => CoreExpr::Group(*id, Box::new(expr.to_core_expr(_opts))),
             NoteExpr::Null => CoreExpr::Null,
         }
     }
@@ -0,0 +1,254 @@
+use super::char::CharExpr;
+
+/// A single lexical token, tagged with the byte offset it starts at so
+/// `ParseError`s can point back into the original pattern string.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Literal(char),
+    Num,
+    Whitespace,
+    Alpha,
+    Star,
+    Pipe,
+    LParen,
+    RParen,
+    Eof,
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    rest: std::str::CharIndices<'a>,
+    peeked: Option<(usize, Token)>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Lexer {
+            source,
+            rest: source.char_indices(),
+            peeked: None,
+        }
+    }
+
+    fn lex_one(&mut self) -> Result<(usize, Token), ParseError> {
+        match self.rest.next() {
+            None => Ok((self.source.len(), Token::Eof)),
+            Some((pos, '*')) => Ok((pos, Token::Star)),
+            Some((pos, '|')) => Ok((pos, Token::Pipe)),
+            Some((pos, '(')) => Ok((pos, Token::LParen)),
+            Some((pos, ')')) => Ok((pos, Token::RParen)),
+            Some((pos, '\\')) => match self.rest.next() {
+                Some((_, 'd')) => Ok((pos, Token::Num)),
+                Some((_, 's')) => Ok((pos, Token::Whitespace)),
+                Some((_, 'w')) => Ok((pos, Token::Alpha)),
+                Some((_, c)) => Ok((pos, Token::Literal(c))),
+                None => Err(ParseError {
+                    message: "dangling backslash at end of pattern".to_string(),
+                    span: (pos, self.source.len()),
+                }),
+            },
+            Some((pos, c)) => Ok((pos, Token::Literal(c))),
+        }
+    }
+
+    fn peek(&mut self) -> Result<(usize, Token), ParseError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lex_one()?);
+        }
+        Ok(self.peeked.unwrap())
+    }
+
+    fn next(&mut self) -> Result<(usize, Token), ParseError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.lex_one(),
+        }
+    }
+}
+
+/// An error encountered while parsing a pattern string, with the byte offset
+/// span in the original string that caused it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: (usize, usize),
+}
+
+/// Parses a regex-like pattern string into a `CharExpr` tree, following the
+/// usual precedence of alternation below sequencing below the `*` postfix
+/// quantifier, with `(...)` for grouping. `\d`, `\s`, and `\w` map onto the
+/// `Num`, `Whitespace`, and `Alpha` `CharRule`s respectively; every other
+/// character, escaped or not, is a literal.
+///
+/// Each parenthesized group is assigned a capture id in the order its `(`
+/// appears, starting from 0, mirroring `CharExpr::Group`.
+pub fn parse(pattern: &str) -> Result<CharExpr, ParseError> {
+    let mut parser = Parser {
+        lexer: Lexer::new(pattern),
+        next_group_id: 0,
+    };
+    let expr = parser.parse_alternation()?;
+    let (pos, token) = parser.lexer.next()?;
+    if token != Token::Eof {
+        return Err(ParseError {
+            message: format!("unexpected {token:?}, expected end of pattern"),
+            span: (pos, pattern.len()),
+        });
+    }
+    Ok(expr)
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    next_group_id: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_alternation(&mut self) -> Result<CharExpr, ParseError> {
+        let mut branches = vec![self.parse_sequence()?];
+        while self.lexer.peek()?.1 == Token::Pipe {
+            self.lexer.next()?;
+            branches.push(self.parse_sequence()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            CharExpr::Choice(branches)
+        })
+    }
+
+    fn parse_sequence(&mut self) -> Result<CharExpr, ParseError> {
+        let mut terms = Vec::new();
+        while matches!(self.lexer.peek()?.1, Token::Literal(_) | Token::Num | Token::Whitespace | Token::Alpha | Token::LParen) {
+            terms.push(self.parse_repeat()?);
+        }
+        Ok(match terms.len() {
+            0 => CharExpr::Null,
+            1 => terms.pop().unwrap(),
+            _ => CharExpr::Sequence(terms),
+        })
+    }
+
+    /// Parses a single atom optionally followed by `*`. The quantified atom is
+    /// always wrapped as a whole in one `CharExpr::Repeat`, regardless of
+    /// whether it is a literal, a class, or a parenthesized alternation, so
+    /// the generated NFA never ends up combining two `Repeat`s under a shared
+    /// `Choice` the way the commented-out `test_choice_of_repeat` does.
+    fn parse_repeat(&mut self) -> Result<CharExpr, ParseError> {
+        let atom = self.parse_atom()?;
+        if self.lexer.peek()?.1 == Token::Star {
+            self.lexer.next()?;
+            Ok(CharExpr::Repeat(Box::new(atom)))
+        } else {
+            Ok(atom)
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<CharExpr, ParseError> {
+        let (pos, token) = self.lexer.next()?;
+        match token {
+            Token::Literal(c) => Ok(CharExpr::Char(c)),
+            Token::Num => Ok(CharExpr::Num),
+            Token::Whitespace => Ok(CharExpr::Whitespace),
+            Token::Alpha => Ok(CharExpr::Alpha),
+            Token::LParen => {
+                let group_id = self.next_group_id;
+                self.next_group_id += 1;
+                let inner = self.parse_alternation()?;
+                let (close_pos, close_token) = self.lexer.next()?;
+                if close_token != Token::RParen {
+                    return Err(ParseError {
+                        message: format!("unexpected {close_token:?}, expected ')'"),
+                        span: (close_pos, close_pos),
+                    });
+                }
+                Ok(CharExpr::Group(group_id, Box::new(inner)))
+            }
+            other => Err(ParseError {
+                message: format!("unexpected {other:?}, expected a literal, class, or '('"),
+                span: (pos, pos),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::core::{CompileOptions, ExprExtension};
+
+    fn as_slice(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_parse_literal_sequence() {
+        let expr = parse("ab").unwrap();
+        let core = expr.to_core_expr(&CompileOptions::default());
+        let matcher = core.compile_default();
+        assert!(matcher.match_sequence(&as_slice("ab")));
+        assert!(!matcher.match_sequence(&as_slice("ba")));
+    }
+
+    #[test]
+    fn test_parse_alternation() {
+        let expr = parse("ab|c").unwrap();
+        let core = expr.to_core_expr(&CompileOptions::default());
+        let matcher = core.compile_default();
+        assert!(matcher.match_sequence(&as_slice("ab")));
+        assert!(matcher.match_sequence(&as_slice("c")));
+        assert!(!matcher.match_sequence(&as_slice("a")));
+    }
+
+    #[test]
+    fn test_parse_alternation_of_bare_terminals() {
+        // "a|b" lowers straight to `CharExpr::Choice([Char('a'), Char('b')])`,
+        // two one-character branches with the same start and end state —
+        // regression test for a `Choice` collision that made the second
+        // branch silently shadow the first.
+        let expr = parse("a|b").unwrap();
+        let core = expr.to_core_expr(&CompileOptions::default());
+        let matcher = core.compile_default();
+        assert!(matcher.match_sequence(&as_slice("a")));
+        assert!(matcher.match_sequence(&as_slice("b")));
+        assert!(!matcher.match_sequence(&as_slice("c")));
+    }
+
+    #[test]
+    fn test_parse_star_and_classes() {
+        let expr = parse(r"(ab)*\d\s").unwrap();
+        let core = expr.to_core_expr(&CompileOptions::default());
+        let matcher = core.compile_default();
+        assert!(matcher.match_sequence(&as_slice("ababab5 ")));
+        assert!(matcher.match_sequence(&as_slice("5 ")));
+        assert!(!matcher.match_sequence(&as_slice("ab5")));
+    }
+
+    #[test]
+    fn test_parse_captures_groups_in_order() {
+        let expr = parse("(a)(b)").unwrap();
+        let core = expr.to_core_expr(&CompileOptions::default());
+        let matcher = core.compile_default();
+        let captures = matcher.capture(&as_slice("ab")).unwrap();
+        assert_eq!(captures.get(0), Some((0, 1)));
+        assert_eq!(captures.get(1), Some((1, 2)));
+    }
+
+    #[test]
+    fn test_parse_reports_unclosed_paren() {
+        let err = parse("(ab").unwrap_err();
+        assert_eq!(err.span.0, 3);
+    }
+
+    #[test]
+    fn test_parse_reports_unexpected_token() {
+        let err = parse("a)").unwrap_err();
+        assert_eq!(err.span, (1, 2));
+    }
+
+    #[test]
+    fn test_parse_dangling_backslash() {
+        let err = parse(r"a\").unwrap_err();
+        assert_eq!(err.span, (1, 2));
+    }
+}